@@ -0,0 +1,181 @@
+//! Token attestation: proves the NTRU signing key in use belongs to a
+//! genuine token, not just one that spoofs the expected USB VID/PID and
+//! manufacturer/product strings.
+//!
+//! The device holds a certificate binding its NTRU public key to a
+//! manufacturer attestation key (`AT+AT`), signed with Ed25519. The host
+//! verifies that certificate against an embedded manufacturer root key,
+//! checks its validity window and revocation status, then has the token
+//! check (`AT+V`) its own NTRU signature over a host-supplied challenge.
+//! That check uses whatever key the token holds: the certificate's
+//! `ntru_pubkey` is not compared against it, so the host does not confirm
+//! that the certified key is the key the token signs with.
+
+use crate::transport::Transport;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CONTEXT_CERT: &[u8] = b"stm32-ntru-tinysign attestation cert v1";
+const CHALLENGE_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum AttestationError {
+    Transport(String),
+    Protocol(String),
+    Verification(String),
+    Expired,
+    Revoked,
+}
+
+impl fmt::Display for AttestationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttestationError::Transport(s) => write!(f, "transport error: {}", s),
+            AttestationError::Protocol(s) => write!(f, "protocol error: {}", s),
+            AttestationError::Verification(s) => write!(f, "verification failed: {}", s),
+            AttestationError::Expired => write!(f, "attestation certificate is expired"),
+            AttestationError::Revoked => write!(f, "attestation key fingerprint is revoked"),
+        }
+    }
+}
+
+/// The manufacturer-signed certificate binding a token's NTRU public key
+/// to a validity window.
+pub struct Cert {
+    pub ntru_pubkey: Vec<u8>,
+    pub not_before: u64,
+    pub not_after: u64,
+    pub manufacturer_sig: [u8; 64],
+}
+
+/// The outcome of a successful attestation: the checked certificate
+/// along with the SHA-256 fingerprint of the attested key, suitable for
+/// pinning in a `.sig` header.
+pub struct Attestation {
+    pub cert: Cert,
+    pub fingerprint: [u8; 32],
+}
+
+fn encode_cert_body(ntru_pubkey: &[u8], not_before: u64, not_after: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(2 + ntru_pubkey.len() + 16);
+    out.extend_from_slice(&(ntru_pubkey.len() as u16).to_le_bytes());
+    out.extend_from_slice(ntru_pubkey);
+    out.extend_from_slice(&not_before.to_le_bytes());
+    out.extend_from_slice(&not_after.to_le_bytes());
+    out
+}
+
+fn parse_cert(bytes: &[u8]) -> Result<Cert, AttestationError> {
+    if bytes.len() < 2 {
+        return Err(AttestationError::Protocol("truncated certificate".into()));
+    }
+    let key_len = u16::from_le_bytes(bytes[0..2].try_into().unwrap()) as usize;
+    if bytes.len() < 2 + key_len + 8 + 8 + 64 {
+        return Err(AttestationError::Protocol("truncated certificate".into()));
+    }
+    let ntru_pubkey = bytes[2..2 + key_len].to_vec();
+    let mut pos = 2 + key_len;
+    let not_before = u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap());
+    pos += 8;
+    let not_after = u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap());
+    pos += 8;
+    let manufacturer_sig: [u8; 64] = bytes[pos..pos + 64].try_into().unwrap();
+    Ok(Cert {
+        ntru_pubkey,
+        not_before,
+        not_after,
+        manufacturer_sig,
+    })
+}
+
+/// Requests and fully verifies a batch attestation certificate from the
+/// token, refusing it if the chain doesn't check out, the window has
+/// lapsed, or the fingerprint is revoked.
+pub fn request_attestation(
+    transport: &mut Transport,
+    root_pubkey: [u8; 32],
+    revoked_fingerprints: &[[u8; 32]],
+) -> Result<Attestation, AttestationError> {
+    let mut challenge = [0u8; CHALLENGE_LEN];
+    rand::thread_rng().fill_bytes(&mut challenge);
+
+    let mut cmd = b"AT+AT ".to_vec();
+    let challenge_hex = challenge
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    cmd.extend_from_slice(challenge_hex.as_bytes());
+    let response = transport
+        .send_command(&cmd)
+        .map_err(|e| AttestationError::Transport(e.to_string()))?;
+    if String::from_utf8_lossy(&response).contains("ERROR") {
+        return Err(AttestationError::Protocol(
+            "token refused attestation request".into(),
+        ));
+    }
+
+    let text = String::from_utf8_lossy(&response);
+    let mut parts = text.split_whitespace();
+    let cert_hex = parts
+        .next()
+        .ok_or_else(|| AttestationError::Protocol("missing certificate in AT+AT response".into()))?;
+    let sig_hex = parts.next().ok_or_else(|| {
+        AttestationError::Protocol("missing challenge signature in AT+AT response".into())
+    })?;
+    let cert_bytes = crate::decode_hex(cert_hex)
+        .map_err(|_| AttestationError::Protocol("malformed certificate hex".into()))?;
+    let challenge_sig = crate::decode_hex(sig_hex)
+        .map_err(|_| AttestationError::Protocol("malformed challenge signature hex".into()))?;
+    let cert = parse_cert(&cert_bytes)?;
+
+    let root_key = VerifyingKey::from_bytes(&root_pubkey)
+        .map_err(|e| AttestationError::Verification(format!("bad root key: {}", e)))?;
+    let mut signed = CONTEXT_CERT.to_vec();
+    signed.extend_from_slice(&encode_cert_body(
+        &cert.ntru_pubkey,
+        cert.not_before,
+        cert.not_after,
+    ));
+    let cert_sig = Signature::from_bytes(&cert.manufacturer_sig);
+    root_key
+        .verify(&signed, &cert_sig)
+        .map_err(|_| AttestationError::Verification("certificate signature invalid".into()))?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if now < cert.not_before || now > cert.not_after {
+        return Err(AttestationError::Expired);
+    }
+
+    let fingerprint: [u8; 32] = Sha256::digest(&cert.ntru_pubkey).into();
+    if revoked_fingerprints.contains(&fingerprint) {
+        return Err(AttestationError::Revoked);
+    }
+
+    // AT+V reuses the existing verify path: the token checks the
+    // challenge signature against its own key. `cert.ntru_pubkey` is not
+    // sent with it or used in this check.
+    let mut verify_data = challenge_sig;
+    verify_data.extend_from_slice(&challenge);
+    let mut verify_cmd = b"AT+V ".to_vec();
+    let verify_data_hex = verify_data
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    verify_cmd.extend_from_slice(verify_data_hex.as_bytes());
+    let verify_resp = transport
+        .send_command(&verify_cmd)
+        .map_err(|e| AttestationError::Transport(e.to_string()))?;
+    if String::from_utf8_lossy(&verify_resp).contains("ERROR") {
+        return Err(AttestationError::Verification(
+            "challenge signature did not verify against the attested key".into(),
+        ));
+    }
+
+    Ok(Attestation { cert, fingerprint })
+}
@@ -1,124 +1,67 @@
+mod attestation;
+mod merkle;
+mod pin;
+mod roughtime;
+mod transport;
+
 use chrono::DateTime;
-use serialport::{available_ports, Error, SerialPort, SerialPortInfo, SerialPortType};
-use sha3::{Digest, Sha3_512};
+use sha2::{Digest as Sha256Digest, Sha256};
+use sha3::Sha3_512;
 use std::{
     io::{self, Write},
     process::ExitCode,
-    thread::sleep,
-    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    time::{SystemTime, UNIX_EPOCH},
     vec,
 };
-
-const SERIAL_TIMEOUT: std::time::Duration = Duration::from_millis(400);
-const INIT_TIMEOUT: std::time::Duration = Duration::from_millis(1500);
-const CMD_TIMEOUT: std::time::Duration = Duration::from_millis(3000);
-const PROBE_GRANUALITY: std::time::Duration = Duration::from_millis(25);
+use transport::Transport;
 
 const NONCE_LEN: usize = (40 + 2) * 2;
 
-/// Locates a device with the correct VID/PID and manufacturer/product
-/// strings in the list of ports returned by `serialport::available_ports`
-fn locate_token(mut ports: Vec<SerialPortInfo>) -> Option<String> {
-    loop {
-        let port = ports.pop()?;
-        if let SerialPortType::UsbPort(port_info) = port.port_type {
-            if port_info.vid == 0x0420
-                && port_info.pid == 0x2137
-                && port_info.manufacturer.unwrap_or("".to_string()) == "ABW"
-                && port_info.product.unwrap_or("".to_string()) == "STM32 NTRU Token"
-            {
-                return Some(port.port_name);
-            }
-        }
-    }
-}
+/// Long-term Ed25519 public key of the Roughtime server trusted for
+/// `--roughtime` timestamping. Swap this out for whichever server's
+/// operator key the deployment wants to trust.
+const ROUGHTIME_ROOT_PUBKEY: [u8; 32] = [
+    0x01, 0x6e, 0x6e, 0x59, 0xe2, 0x7d, 0x9e, 0x00, 0x53, 0x4d, 0xf6, 0x2d, 0x76, 0x9a, 0x11, 0x32,
+    0xe2, 0x1b, 0xf7, 0xc3, 0x3e, 0x85, 0x52, 0x21, 0x80, 0x5e, 0x68, 0x71, 0x46, 0xe2, 0xdc, 0xd0,
+];
 
-/// Initializes communication with the device by sending a newline and waiting
-/// for the device to send a response ending with a newline.
-/// Will timeout after INIT_TIMEOUT milliseconds of no response.
-fn init_communication(port: &mut Box<dyn SerialPort>) -> Result<(), Error> {
-    port.set_timeout(SERIAL_TIMEOUT)?;
-    port.write("\r\n".as_bytes())?;
-    let mut buf: Vec<u8> = vec![0; 64];
-    let mut res: Vec<u8> = Vec::with_capacity(1024);
-    let start = Instant::now();
-    loop {
-        match port.read(&mut buf) {
-            Ok(n_read) => {
-                if n_read > 0 {
-                    res.extend_from_slice(&buf[..n_read]);
-                    buf = vec![0; 64];
-                }
-            }
-            Err(_) => {}
-        }
-        if res.iter().filter(|&&c| c == b'\n').count() > 0 {
-            return Ok(());
-        }
-        if Instant::now().duration_since(start) > INIT_TIMEOUT {
-            return Err(Error::new(serialport::ErrorKind::NoDevice, "No response"));
-        }
-        sleep(PROBE_GRANUALITY);
-    }
-}
+/// Long-term Ed25519 public key of the "STM32 NTRU Token" manufacturer,
+/// used to verify batch attestation certificates returned by `AT+AT`.
+const MANUFACTURER_ROOT_PUBKEY: [u8; 32] = [
+    0x7c, 0x3e, 0x2a, 0x9d, 0x41, 0x0b, 0x88, 0xf5, 0x6a, 0x12, 0xcd, 0x77, 0x03, 0x9e, 0x5f, 0x64,
+    0xb8, 0x2d, 0x91, 0xa6, 0x3c, 0x47, 0xe0, 0x1d, 0x5b, 0x9a, 0x8e, 0x22, 0xf1, 0x60, 0xd4, 0x37,
+];
 
-/// Sends a command to the device and awaits n newline-terminated responses.
-/// Will timeout after CMD_TIMEOUT milliseconds of no response.
-fn send_and_read_resp(
-    port: &mut Box<dyn SerialPort>,
-    res: &mut Vec<u8>,
-    cmd: &[u8],
-    mut n: i32,
-) -> Result<(), Error> {
-    port.write_all(&cmd)?;
-    let mut buf: Vec<u8> = vec![0; 64];
-    let start = Instant::now();
-    loop {
-        match port.read(&mut buf) {
-            Ok(n_read) => {
-                if n_read > 0 {
-                    res.extend_from_slice(&buf[..n_read]);
-                    n -= buf.iter().filter(|&&c| c == b'\n').count() as i32;
-                    buf = vec![0; 64];
-                }
-            }
-            Err(_) => {}
-        }
-        if n <= 0 {
-            while n <= 0 {
-                let last_ch = *res.last().unwrap_or(&b'\0');
-                if last_ch == b'\n' || last_ch == b'\r'{
-                    while *res.last().unwrap_or(&b'\0') == b'\n' || *res.last().unwrap_or(&b'\0') == b'\r' {
-                        res.pop();
-                    }
-                n += 1;
-                } else {
-                    res.pop();
-                }
-            }
-            return Ok(());
-        }
-        if Instant::now().duration_since(start) > CMD_TIMEOUT {
-            return Err(Error::new(serialport::ErrorKind::NoDevice, "No response"));
-        }
-        sleep(PROBE_GRANUALITY);
-    }
-}
+/// Fingerprints (SHA-256 of the attested NTRU public key) of tokens
+/// whose attestation should no longer be trusted.
+const REVOKED_FINGERPRINTS: [[u8; 32]; 0] = [];
+
+type GetFilesResult = (
+    Vec<u8>,
+    Option<std::fs::File>,
+    Option<roughtime::TimestampProof>,
+    Option<(usize, Vec<merkle::ProofStep>)>,
+    Option<[u8; 32]>,
+);
 
-fn get_files(file_path: &str) -> Result<(Vec<u8>, Option<std::fs::File>), std::io::Error> {
+fn get_files(
+    file_path: &str,
+    roughtime_proof: Option<&roughtime::TimestampProof>,
+) -> Result<GetFilesResult, std::io::Error> {
     let mut file = std::fs::File::open(file_path)?;
     if !file_path.ends_with(".sig") {
         // timestamp + | + sha3_512 of file
         let mut hasher = Sha3_512::new();
         std::io::copy(&mut file, &mut hasher)?;
         let mut data: Vec<u8> = Vec::with_capacity(Sha3_512::output_size() + 10);
-        data.extend_from_slice(
-            &SystemTime::now()
+        let timestamp_secs = match roughtime_proof {
+            Some(proof) => proof.midpoint_us / 1_000_000,
+            None => SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
-                .as_secs()
-                .to_le_bytes(),
-        );
+                .as_secs(),
+        };
+        data.extend_from_slice(&timestamp_secs.to_le_bytes());
         data.extend_from_slice(&"|".as_bytes());
         data.extend_from_slice(hasher.finalize().as_slice());
 
@@ -127,11 +70,16 @@ fn get_files(file_path: &str) -> Result<(Vec<u8>, Option<std::fs::File>), std::i
             .write(true)
             .create(true)
             .open(file_path.to_string() + ".sig")?;
-        Ok((data, Some(sig_file)))
+        Ok((data, Some(sig_file), None, None, None))
     } else {
-        // read the signature file
-        let mut data = std::fs::read(file_path)?;
-        while *data.last().unwrap() == b'\n' || *data.last().unwrap() == b'\r' {
+        // read the signature file; optional trailing lines hold a
+        // Roughtime attestation ("RGHT ..."), a Merkle inclusion proof
+        // ("MRKL ...") for a batch-signed root, and/or the pinned
+        // token attestation fingerprint ("ATST ...")
+        let raw = std::fs::read(file_path)?;
+        let mut lines = raw.split(|&b| b == b'\n');
+        let mut data = lines.next().unwrap_or(&[]).to_vec();
+        while matches!(data.last(), Some(b'\n') | Some(b'\r')) {
             data.pop();
         }
         if data.len() % 2 != 0 || data.len() < NONCE_LEN + Sha3_512::output_size() * 2 + 4 {
@@ -140,7 +88,29 @@ fn get_files(file_path: &str) -> Result<(Vec<u8>, Option<std::fs::File>), std::i
                 "Invalid signature file",
             ));
         }
-        Ok((data, None))
+        let mut roughtime_proof = None;
+        let mut merkle_proof = None;
+        let mut attestation_fingerprint = None;
+        for line in lines {
+            let mut line = line.to_vec();
+            while matches!(line.last(), Some(b'\n') | Some(b'\r')) {
+                line.pop();
+            }
+            if let Some(hex) = line.strip_prefix(b"RGHT ") {
+                roughtime_proof = decode_hex(&String::from_utf8_lossy(hex))
+                    .ok()
+                    .and_then(|bytes| roughtime::deserialize(&bytes).ok());
+            } else if let Some(hex) = line.strip_prefix(b"MRKL ") {
+                merkle_proof = decode_hex(&String::from_utf8_lossy(hex))
+                    .ok()
+                    .and_then(|bytes| merkle::deserialize_proof(&bytes));
+            } else if let Some(hex) = line.strip_prefix(b"ATST ") {
+                attestation_fingerprint = decode_hex(&String::from_utf8_lossy(hex))
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok());
+            }
+        }
+        Ok((data, None, roughtime_proof, merkle_proof, attestation_fingerprint))
     }
 }
 
@@ -151,57 +121,178 @@ pub fn decode_hex(s: &str) -> Result<Vec<u8>, std::num::ParseIntError> {
         .collect()
 }
 
-fn main() -> ExitCode {
-    let file_path = std::env::args().nth(1);
-    if let None = file_path {
-        eprintln!("Argument required: path to file to sign");
-        return ExitCode::FAILURE;
-    }
-    let file_path = file_path.unwrap();
-    let port_name = match available_ports() {
-        Err(e) => {
-            eprintln!("Error: {}", e);
+/// Signs a batch of files with a single token operation: hashes every
+/// file, builds a Merkle tree over those hashes, sends only the 64-byte
+/// root (prefixed with the timestamp and `|`, as for a single file) to the
+/// token for one `AT+S`, then writes each file a standalone
+/// `.sig` containing the shared root signature plus its own inclusion
+/// proof.
+fn sign_batch(
+    transport: &mut Transport,
+    file_paths: &[String],
+    roughtime_proof: Option<&roughtime::TimestampProof>,
+    pin_session: Option<&pin::PinSession>,
+    attested_fingerprint: [u8; 32],
+) -> ExitCode {
+    let mut file_hashes: Vec<[u8; 64]> = Vec::with_capacity(file_paths.len());
+    for path in file_paths {
+        if path.ends_with(".sig") {
+            eprintln!("Error: batch signing does not accept .sig files ({})", path);
             return ExitCode::FAILURE;
         }
-        Ok(ports) => {
-            if let Some(port_name) = locate_token(ports) {
-                port_name
-            } else {
-                eprintln!("Error: Token not found");
+        let mut file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Error acquiring file {}: {}", path, e);
                 return ExitCode::FAILURE;
             }
+        };
+        let mut hasher = Sha3_512::new();
+        if let Err(e) = io::copy(&mut file, &mut hasher) {
+            eprintln!("Error hashing file {}: {}", path, e);
+            return ExitCode::FAILURE;
         }
+        file_hashes.push(hasher.finalize().as_slice().try_into().unwrap());
+    }
+
+    let tree = merkle::Tree::build(&file_hashes);
+    let root = tree.root();
+
+    let timestamp_secs = match roughtime_proof {
+        Some(proof) => proof.midpoint_us / 1_000_000,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
     };
-    let mut handle;
-    if let Ok(port) = serialport::new(port_name, 115200).open() {
-        handle = port;
-    } else {
-        eprintln!("Error: Failed to open serial port");
+    let mut data: Vec<u8> = Vec::with_capacity(Sha3_512::output_size() + 10);
+    data.extend_from_slice(&timestamp_secs.to_le_bytes());
+    data.extend_from_slice(&"|".as_bytes());
+    data.extend_from_slice(&root);
+
+    let mut cmd = "AT+S ".as_bytes().to_vec();
+    let hex_data = data.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    cmd.extend_from_slice(hex_data.as_bytes());
+    if let Some(session) = pin_session {
+        let auth_param = session.auth_param(&Sha256::digest(&data));
+        cmd.push(b' ');
+        cmd.extend(auth_param.iter().map(|b| format!("{:02x}", b)).collect::<String>().into_bytes());
+    }
+    let response = match transport.send_command(&cmd) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Error while signing: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if String::from_utf8_lossy(&response).contains("ERROR") {
+        eprintln!("Signature creation failed");
         return ExitCode::FAILURE;
     }
-    if let Err(e) = init_communication(&mut handle) {
-        eprintln!("Error while starting up: {}", e);
+    let mut root_sig = response;
+    root_sig.extend_from_slice("\r\n".as_bytes());
+
+    for (i, path) in file_paths.iter().enumerate() {
+        let mut sig_file = match std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(path.to_string() + ".sig")
+        {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("Error writing signature for {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Err(e) = sig_file.write_all(&root_sig) {
+            eprintln!("Error writing signature for {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+        if let Some(proof) = roughtime_proof {
+            let hex_proof = roughtime::serialize(proof)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            if let Err(e) = writeln!(sig_file, "RGHT {}\r", hex_proof) {
+                eprintln!("Error writing Roughtime proof for {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+        let proof_steps = tree.proof(i);
+        let hex_proof = merkle::serialize_proof(i, &proof_steps)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        if let Err(e) = writeln!(sig_file, "MRKL {}\r", hex_proof) {
+            eprintln!("Error writing Merkle proof for {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+        let hex_fingerprint = attested_fingerprint
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        if let Err(e) = writeln!(sig_file, "ATST {}\r", hex_fingerprint) {
+            eprintln!("Error writing attestation fingerprint for {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+        println!("Signature written to file: {}", path.to_string() + ".sig");
+    }
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut file_paths: Vec<String> = Vec::new();
+    let mut roughtime_addr: Option<String> = None;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--roughtime" {
+            roughtime_addr = match iter.next() {
+                Some(addr) => Some(addr),
+                None => {
+                    eprintln!("--roughtime requires a <host:port> argument");
+                    return ExitCode::FAILURE;
+                }
+            };
+        } else {
+            file_paths.push(arg);
+        }
+    }
+    if file_paths.is_empty() {
+        eprintln!("Argument required: path(s) to file(s) to sign, or a .sig file to verify");
         return ExitCode::FAILURE;
     }
 
-    let (data, sig_file) = match get_files(&file_path) {
-        Ok((data, sig_file)) => (data, sig_file),
+    let roughtime_proof = match &roughtime_addr {
+        Some(addr) => match roughtime::get_attested_time(addr, ROUGHTIME_ROOT_PUBKEY) {
+            Ok(proof) => Some(proof),
+            Err(e) => {
+                eprintln!("Error obtaining Roughtime timestamp: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    let mut transport = match Transport::connect() {
+        Ok(transport) => transport,
         Err(e) => {
-            eprintln!("Error acquiring file: {}", e);
+            eprintln!("Error while starting up: {}", e);
             return ExitCode::FAILURE;
         }
     };
-
-    // Response buffer
-    let mut buffer: Vec<u8> = Vec::with_capacity(10240);
+    println!("Found a token on {}", transport.port_name());
 
     // Get device info
-    if let Err(e) = send_and_read_resp(&mut handle, &mut buffer, "AT+I\r\n".as_bytes(), 4) {
-        eprintln!("Error getting device info: {}", e);
-        return ExitCode::FAILURE;
-    }
+    let buffer = match transport.send_command(b"AT+I") {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            eprintln!("Error getting device info: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
     let info_msg = String::from_utf8_lossy(&buffer);
-    println!("Found a token! Device info: \r\n{}", info_msg);
+    println!("Device info: \r\n{}", info_msg);
     // Get maximum accepted message length from device info
     let max_msg_len = info_msg
         .lines()
@@ -217,6 +308,66 @@ fn main() -> ExitCode {
         eprintln!("Error: Device message capacity insufficient");
         return ExitCode::FAILURE;
     }
+
+    let attested = match attestation::request_attestation(
+        &mut transport,
+        MANUFACTURER_ROOT_PUBKEY,
+        &REVOKED_FINGERPRINTS,
+    ) {
+        Ok(attested) => attested,
+        Err(e) => {
+            eprintln!("Error: Token attestation failed: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "Token attested. NTRU key fingerprint: {}",
+        attested
+            .fingerprint
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+
+    let pin_session = if pin::requires_pin(&info_msg) {
+        let entered_pin = match rpassword::prompt_password("Token PIN: ") {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("Error reading PIN: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        match pin::establish(&mut transport, &entered_pin) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                eprintln!("Error establishing PIN session: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        None
+    };
+
+    if file_paths.len() > 1 {
+        return sign_batch(
+            &mut transport,
+            &file_paths,
+            roughtime_proof.as_ref(),
+            pin_session.as_ref(),
+            attested.fingerprint,
+        );
+    }
+    let file_path = file_paths.into_iter().next().unwrap();
+
+    let (data, sig_file, stored_roughtime_proof, merkle_proof, stored_attestation_fingerprint) =
+        match get_files(&file_path, roughtime_proof.as_ref()) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Error acquiring file: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+
     match sig_file {
         // We are signing the file
         Some(mut sig_file) => {
@@ -226,35 +377,61 @@ fn main() -> ExitCode {
                 .map(|b| format!("{:02x}", b))
                 .collect::<String>();
             cmd.extend_from_slice(hex_data.as_bytes());
-            cmd.extend_from_slice("\r\n".as_bytes());
-            buffer.clear();
-            if let Err(e) = send_and_read_resp(&mut handle, &mut buffer, &cmd, 1) {
-                eprintln!("Error while signing: {}", e);
-                return ExitCode::FAILURE;
+            if let Some(session) = &pin_session {
+                let auth_param = session.auth_param(&Sha256::digest(&data));
+                cmd.push(b' ');
+                cmd.extend(auth_param.iter().map(|b| format!("{:02x}", b)).collect::<String>().into_bytes());
             }
-            if String::from_utf8_lossy(&buffer).contains("ERROR") {
+            let mut response = match transport.send_command(&cmd) {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("Error while signing: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            if String::from_utf8_lossy(&response).contains("ERROR") {
                 eprintln!("Signature creation failed");
                 return ExitCode::FAILURE;
             }
-            buffer.extend_from_slice("\r\n".as_bytes());
-            if let Err(e) = sig_file.write_all(&buffer)
+            response.extend_from_slice("\r\n".as_bytes());
+            if let Err(e) = sig_file.write_all(&response)
             {
                 eprintln!("Error writing signature to file: {}", e);
                 return ExitCode::FAILURE;
             }
+            if let Some(proof) = &roughtime_proof {
+                let hex_proof = roughtime::serialize(proof)
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                if let Err(e) = writeln!(sig_file, "RGHT {}\r", hex_proof) {
+                    eprintln!("Error writing Roughtime proof to file: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            let hex_fingerprint = attested
+                .fingerprint
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            if let Err(e) = writeln!(sig_file, "ATST {}\r", hex_fingerprint) {
+                eprintln!("Error writing attestation fingerprint to file: {}", e);
+                return ExitCode::FAILURE;
+            }
             println!("Signature written to file: {}", file_path.to_string() + ".sig");
         }
         // We are verifying the signature
         None => {
             let mut cmd = "AT+V ".as_bytes().to_vec();
             cmd.extend_from_slice(&data);
-            cmd.extend_from_slice("\r\n".as_bytes());
-            buffer.clear();
-            if let Err(e) = send_and_read_resp(&mut handle, &mut buffer, &cmd, 1) {
-                eprintln!("Error while verifying: {}", e);
-                return ExitCode::FAILURE;
-            }
-            if String::from_utf8_lossy(&buffer).contains("ERROR") {
+            let response = match transport.send_command(&cmd) {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("Error while verifying: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            if String::from_utf8_lossy(&response).contains("ERROR") {
                 eprintln!("Signature is invalid");
                 return ExitCode::FAILURE;
             }
@@ -290,19 +467,72 @@ fn main() -> ExitCode {
                 }
             };
             io::copy(&mut file, &mut hasher).unwrap();
-            let file_hash = hasher.finalize().to_vec();
-            
-            // Compare the hashes
-            if file_hash != hash {
-                eprintln!("Error: Signature does not match base file");
-                return ExitCode::FAILURE;
+            let file_hash: [u8; 64] = hasher.finalize().as_slice().try_into().unwrap();
+
+            match &merkle_proof {
+                // Batch-signed file: the signature covers the Merkle root,
+                // so fold this file's hash up its inclusion proof first.
+                Some((_, proof)) => {
+                    let folded_root = merkle::fold_proof(&file_hash, proof);
+                    if folded_root.to_vec() != hash {
+                        eprintln!("Error: Signature does not match base file");
+                        return ExitCode::FAILURE;
+                    }
+                }
+                None => {
+                    if file_hash.to_vec() != hash {
+                        eprintln!("Error: Signature does not match base file");
+                        return ExitCode::FAILURE;
+                    }
+                }
             }
 
-            println!(
-                "Signature verified successfully.\r\nCreation time: {}",
-                timestamp.to_rfc2822()
-            );
+            match stored_roughtime_proof {
+                Some(proof) => {
+                    if let Err(e) = roughtime::verify(&proof, ROUGHTIME_ROOT_PUBKEY) {
+                        eprintln!("Error: Roughtime attestation invalid: {}", e);
+                        return ExitCode::FAILURE;
+                    }
+                    let attested_time = match DateTime::from_timestamp(
+                        (proof.midpoint_us / 1_000_000) as i64,
+                        ((proof.midpoint_us % 1_000_000) * 1000) as u32,
+                    ) {
+                        Some(t) => t,
+                        None => {
+                            eprintln!("Error: Invalid attested timestamp");
+                            return ExitCode::FAILURE;
+                        }
+                    };
+                    println!(
+                        "Signature verified successfully.\r\nAttested creation time (Roughtime, ±{}µs): {}",
+                        proof.radius_us,
+                        attested_time.to_rfc2822()
+                    );
+                }
+                None => {
+                    println!(
+                        "Signature verified successfully.\r\nCreation time: {}",
+                        timestamp.to_rfc2822()
+                    );
+                }
+            }
             println!("Matches file: {}", &file_path);
+            if let Some(fingerprint) = stored_attestation_fingerprint {
+                if fingerprint != attested.fingerprint {
+                    eprintln!(
+                        "Error: signature was produced by a different attested token than the one currently connected"
+                    );
+                    return ExitCode::FAILURE;
+                }
+                let hex_fingerprint = fingerprint
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                println!(
+                    "Signed by attested key: {} (matches the currently attested token)",
+                    hex_fingerprint
+                );
+            }
         }
     }
     return ExitCode::SUCCESS;
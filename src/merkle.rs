@@ -0,0 +1,131 @@
+//! Domain-separated Merkle tree over per-file SHA3-512 hashes, used by
+//! batch signing so that N files can share a single token signature over
+//! the tree root while still verifying standalone via an inclusion proof.
+
+use sha3::{Digest, Sha3_512};
+
+fn leaf_hash(file_hash: &[u8; 64]) -> [u8; 64] {
+    let mut hasher = Sha3_512::new();
+    hasher.update([0x00]);
+    hasher.update(file_hash);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 64], right: &[u8; 64]) -> [u8; 64] {
+    let mut hasher = Sha3_512::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// One sibling hash and whether it sits to the right of the accumulator
+/// at that level.
+#[derive(Clone)]
+pub struct ProofStep {
+    pub sibling: [u8; 64],
+    pub sibling_is_right: bool,
+}
+
+/// A tree built over the leaf hashes of a batch of files, kept around
+/// just long enough to pull out the root and each file's inclusion proof.
+pub struct Tree {
+    levels: Vec<Vec<[u8; 64]>>,
+}
+
+impl Tree {
+    /// Builds the tree bottom-up. Odd nodes at a level are promoted to
+    /// the next level unchanged rather than duplicated.
+    pub fn build(file_hashes: &[[u8; 64]]) -> Tree {
+        let mut levels = Vec::new();
+        let mut level: Vec<[u8; 64]> = file_hashes.iter().map(leaf_hash).collect();
+        levels.push(level.clone());
+        while level.len() > 1 {
+            let mut next = Vec::with_capacity((level.len() + 1) / 2);
+            let mut i = 0;
+            while i < level.len() {
+                if i + 1 < level.len() {
+                    next.push(node_hash(&level[i], &level[i + 1]));
+                } else {
+                    next.push(level[i]);
+                }
+                i += 2;
+            }
+            levels.push(next.clone());
+            level = next;
+        }
+        Tree { levels }
+    }
+
+    pub fn root(&self) -> [u8; 64] {
+        self.levels.last().unwrap()[0]
+    }
+
+    /// Ordered sibling hashes (leaf to root) for the file at `index`,
+    /// skipping levels where the node was promoted unchanged (no sibling
+    /// to record there).
+    pub fn proof(&self, mut index: usize) -> Vec<ProofStep> {
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling_index = if index % 2 == 0 { index + 1 } else { index - 1 };
+            if sibling_index < level.len() {
+                proof.push(ProofStep {
+                    sibling: level[sibling_index],
+                    sibling_is_right: index % 2 == 0,
+                });
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+/// Recomputes the root a file's hash folds up to given its inclusion
+/// proof, for use on the verifying side.
+pub fn fold_proof(file_hash: &[u8; 64], proof: &[ProofStep]) -> [u8; 64] {
+    let mut acc = leaf_hash(file_hash);
+    for step in proof {
+        acc = if step.sibling_is_right {
+            node_hash(&acc, &step.sibling)
+        } else {
+            node_hash(&step.sibling, &acc)
+        };
+    }
+    acc
+}
+
+/// Serializes an inclusion proof as: leaf index (u32 LE), step count (u32
+/// LE), then per step a direction byte followed by the 64-byte sibling.
+pub fn serialize_proof(index: usize, proof: &[ProofStep]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(8 + proof.len() * 65);
+    out.extend_from_slice(&(index as u32).to_le_bytes());
+    out.extend_from_slice(&(proof.len() as u32).to_le_bytes());
+    for step in proof {
+        out.push(step.sibling_is_right as u8);
+        out.extend_from_slice(&step.sibling);
+    }
+    out
+}
+
+pub fn deserialize_proof(data: &[u8]) -> Option<(usize, Vec<ProofStep>)> {
+    if data.len() < 8 {
+        return None;
+    }
+    let index = u32::from_le_bytes(data[0..4].try_into().ok()?) as usize;
+    let count = u32::from_le_bytes(data[4..8].try_into().ok()?) as usize;
+    let mut pos = 8;
+    let mut proof = Vec::with_capacity(count);
+    for _ in 0..count {
+        if pos + 65 > data.len() {
+            return None;
+        }
+        let sibling_is_right = data[pos] != 0;
+        let sibling: [u8; 64] = data[pos + 1..pos + 65].try_into().ok()?;
+        proof.push(ProofStep {
+            sibling,
+            sibling_is_right,
+        });
+        pos += 65;
+    }
+    Some((index, proof))
+}
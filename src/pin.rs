@@ -0,0 +1,166 @@
+//! CTAP2-style client-PIN handshake layered on top of the AT protocol.
+//!
+//! Before the token will release a signature it requires proof the
+//! caller knows the PIN, without the PIN ever crossing the wire in the
+//! clear. The host and token first run an ECDH key agreement (`AT+KA`),
+//! derive a shared secret via HKDF, and use it to encrypt the PIN hash
+//! when asking for a short-lived `pinToken` (`AT+PV`). Every subsequent
+//! `AT+S` then carries a `pinUvAuthParam`: an HMAC-SHA-256 over the
+//! SHA-256 hash of the signed message, keyed by that `pinToken`, which
+//! the token checks
+//! before it will sign.
+
+use crate::transport::Transport;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+const HKDF_INFO: &[u8] = b"stm32-ntru-tinysign pinUvAuthToken v1";
+
+#[derive(Debug)]
+pub enum PinError {
+    Transport(String),
+    Protocol(String),
+    Crypto(String),
+}
+
+impl fmt::Display for PinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinError::Transport(s) => write!(f, "transport error: {}", s),
+            PinError::Protocol(s) => write!(f, "protocol error: {}", s),
+            PinError::Crypto(s) => write!(f, "crypto error: {}", s),
+        }
+    }
+}
+
+/// An established PIN session: the shared secret lets the host encrypt
+/// auth material; the `pinToken` (once obtained) authorizes signing for
+/// the rest of the run.
+pub struct PinSession {
+    session_key: [u8; 32],
+    pin_token: Vec<u8>,
+}
+
+fn decode_hex_response(resp: &[u8]) -> Result<Vec<u8>, PinError> {
+    let text = String::from_utf8_lossy(resp);
+    let text = text.trim();
+    crate::decode_hex(text).map_err(|_| PinError::Protocol("malformed hex response".into()))
+}
+
+fn hkdf_derive(shared_secret: &[u8; 32]) -> [u8; 32] {
+    let hk = Hkdf::<Sha256>::new(None, shared_secret);
+    let mut out = [0u8; 32];
+    hk.expand(HKDF_INFO, &mut out)
+        .expect("32 bytes is a valid HKDF output length");
+    out
+}
+
+fn aead_encrypt(key: &[u8; 32], nonce: &[u8; 12], plaintext: &[u8]) -> Result<Vec<u8>, PinError> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| PinError::Crypto(e.to_string()))?;
+    cipher
+        .encrypt(Nonce::from_slice(nonce), plaintext)
+        .map_err(|e| PinError::Crypto(e.to_string()))
+}
+
+fn aead_decrypt(key: &[u8; 32], nonce: &[u8; 12], ciphertext: &[u8]) -> Result<Vec<u8>, PinError> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| PinError::Crypto(e.to_string()))?;
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|e| PinError::Crypto(e.to_string()))
+}
+
+/// Returns true if the token's `AT+I` banner reports that it is
+/// PIN-protected.
+pub fn requires_pin(info_msg: &str) -> bool {
+    info_msg.to_uppercase().contains("PIN")
+}
+
+/// Runs the ECDH key agreement and PIN verification round trips and
+/// returns a session whose `pinToken` can authorize `AT+S` commands for
+/// the rest of the run.
+pub fn establish(transport: &mut Transport, pin: &str) -> Result<PinSession, PinError> {
+    let host_secret = EphemeralSecret::random_from_rng(rand::thread_rng());
+    let host_public = PublicKey::from(&host_secret);
+
+    let mut cmd = b"AT+KA ".to_vec();
+    let host_public_hex = host_public
+        .as_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    cmd.extend_from_slice(host_public_hex.as_bytes());
+    let response = transport
+        .send_command(&cmd)
+        .map_err(|e| PinError::Transport(e.to_string()))?;
+    let token_public_bytes = decode_hex_response(&response)?;
+    let token_public: [u8; 32] = token_public_bytes
+        .try_into()
+        .map_err(|_| PinError::Protocol("bad AT+KA public key length".into()))?;
+    let shared_secret = host_secret.diffie_hellman(&PublicKey::from(token_public));
+    let session_key = hkdf_derive(shared_secret.as_bytes());
+
+    let mut pin_hash_full = Sha256::new();
+    pin_hash_full.update(pin.as_bytes());
+    let pin_hash_full = pin_hash_full.finalize();
+    let pin_hash_left16 = &pin_hash_full[..16];
+
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let encrypted_pin_hash = aead_encrypt(&session_key, &nonce, pin_hash_left16)?;
+
+    let mut cmd = b"AT+PV ".to_vec();
+    let nonce_hex = nonce.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    cmd.extend_from_slice(nonce_hex.as_bytes());
+    let encrypted_pin_hash_hex = encrypted_pin_hash
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    cmd.extend_from_slice(encrypted_pin_hash_hex.as_bytes());
+    let response = transport
+        .send_command(&cmd)
+        .map_err(|e| PinError::Transport(e.to_string()))?;
+    if String::from_utf8_lossy(&response).contains("ERROR") {
+        return Err(PinError::Protocol(
+            "token rejected PIN (incorrect PIN or locked out)".into(),
+        ));
+    }
+    let response = decode_hex_response(&response)?;
+    if response.len() < 12 {
+        return Err(PinError::Protocol("AT+PV response too short".into()));
+    }
+    let (resp_nonce, resp_ciphertext) = response.split_at(12);
+    let resp_nonce: [u8; 12] = resp_nonce
+        .try_into()
+        .map_err(|_| PinError::Protocol("bad AT+PV nonce length".into()))?;
+    let pin_token = aead_decrypt(&session_key, &resp_nonce, resp_ciphertext)?;
+
+    Ok(PinSession {
+        session_key,
+        pin_token,
+    })
+}
+
+impl PinSession {
+    /// Computes `pinUvAuthParam` for a command whose payload hashes to
+    /// `message_hash`: an HMAC-SHA-256 over that hash, keyed by the
+    /// session's `pinToken`.
+    pub fn auth_param(&self, message_hash: &[u8]) -> Vec<u8> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.pin_token)
+            .expect("HMAC accepts keys of any length");
+        mac.update(message_hash);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Exposed for tests/debugging only; the session key never leaves
+    /// this module in normal operation.
+    #[allow(dead_code)]
+    fn session_key(&self) -> &[u8; 32] {
+        &self.session_key
+    }
+}
@@ -0,0 +1,397 @@
+//! Minimal Roughtime client used to obtain a cryptographically attested
+//! timestamp for a signature, independent of the signer's local clock.
+//!
+//! Implements just enough of the wire protocol (tagged messages, a single
+//! UDP round trip, and full chain verification of SIG/CERT/DELE/Merkle
+//! path) to embed a verifiable timestamp proof alongside an NTRU
+//! signature.
+
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rand::RngCore;
+use sha2::{Digest, Sha512};
+use std::fmt;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+const REQUEST_LEN: usize = 1024;
+const RECV_TIMEOUT: Duration = Duration::from_millis(2000);
+
+const TAG_NONC: [u8; 4] = *b"NONC";
+const TAG_PAD: [u8; 4] = *b"PAD\xff";
+const TAG_SREP: [u8; 4] = *b"SREP";
+const TAG_SIG: [u8; 4] = *b"SIG\x00";
+const TAG_CERT: [u8; 4] = *b"CERT";
+const TAG_INDX: [u8; 4] = *b"INDX";
+const TAG_PATH: [u8; 4] = *b"PATH";
+const TAG_ROOT: [u8; 4] = *b"ROOT";
+const TAG_MIDP: [u8; 4] = *b"MIDP";
+const TAG_RADI: [u8; 4] = *b"RADI";
+const TAG_DELE: [u8; 4] = *b"DELE";
+const TAG_MINT: [u8; 4] = *b"MINT";
+const TAG_MAXT: [u8; 4] = *b"MAXT";
+
+const CONTEXT_SREP: &[u8] = b"RoughTime v1 response signature";
+const CONTEXT_DELE: &[u8] = b"RoughTime v1 delegation signature--";
+
+#[derive(Debug)]
+pub enum RoughtimeError {
+    Io(std::io::Error),
+    Protocol(String),
+    Verification(String),
+}
+
+impl fmt::Display for RoughtimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoughtimeError::Io(e) => write!(f, "I/O error: {}", e),
+            RoughtimeError::Protocol(s) => write!(f, "Protocol error: {}", s),
+            RoughtimeError::Verification(s) => write!(f, "Verification failed: {}", s),
+        }
+    }
+}
+
+impl From<std::io::Error> for RoughtimeError {
+    fn from(e: std::io::Error) -> Self {
+        RoughtimeError::Io(e)
+    }
+}
+
+/// A single tagged Roughtime attestation: the pieces a verifier needs to
+/// re-run the chain-of-trust checks without talking to the server again.
+#[derive(Clone)]
+pub struct TimestampProof {
+    pub nonce: [u8; 64],
+    pub root_pubkey: [u8; 32],
+    pub dele: Vec<u8>,
+    pub dele_sig: [u8; 64],
+    pub srep: Vec<u8>,
+    pub srep_sig: [u8; 64],
+    pub index: u32,
+    pub path: Vec<[u8; 64]>,
+    pub midpoint_us: u64,
+    pub radius_us: u32,
+}
+
+fn sha512(parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// Encodes a set of tags (already in ascending tag-value order) into a
+/// Roughtime message: num_tags, offsets, tags, then the concatenated
+/// values.
+fn encode_message(fields: &[([u8; 4], &[u8])]) -> Vec<u8> {
+    let num_tags = fields.len() as u32;
+    let mut out = Vec::new();
+    out.extend_from_slice(&num_tags.to_le_bytes());
+    let mut offset = 0u32;
+    for (_, value) in &fields[..fields.len().saturating_sub(1)] {
+        offset += value.len() as u32;
+        out.extend_from_slice(&offset.to_le_bytes());
+    }
+    for (tag, _) in fields {
+        out.extend_from_slice(tag);
+    }
+    for (_, value) in fields {
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+/// Decodes a Roughtime tagged message into a tag -> value map.
+fn decode_message(buf: &[u8]) -> Result<std::collections::HashMap<[u8; 4], Vec<u8>>, RoughtimeError> {
+    if buf.len() < 4 {
+        return Err(RoughtimeError::Protocol("message too short".into()));
+    }
+    let num_tags = u32::from_le_bytes(buf[0..4].try_into().unwrap()) as usize;
+    let header_len = 4 + 4 * num_tags.saturating_sub(1) + 4 * num_tags;
+    if buf.len() < header_len {
+        return Err(RoughtimeError::Protocol("truncated header".into()));
+    }
+    let mut offsets = Vec::with_capacity(num_tags);
+    offsets.push(0u32);
+    for i in 0..num_tags.saturating_sub(1) {
+        let start = 4 + i * 4;
+        offsets.push(u32::from_le_bytes(buf[start..start + 4].try_into().unwrap()));
+    }
+    let tags_start = 4 + 4 * num_tags.saturating_sub(1);
+    let values_start = tags_start + 4 * num_tags;
+    let mut map = std::collections::HashMap::with_capacity(num_tags);
+    for i in 0..num_tags {
+        let tag: [u8; 4] = buf[tags_start + i * 4..tags_start + i * 4 + 4]
+            .try_into()
+            .unwrap();
+        let start = values_start + offsets[i] as usize;
+        let end = if i + 1 < num_tags {
+            values_start + offsets[i + 1] as usize
+        } else {
+            buf.len()
+        };
+        if end > buf.len() || start > end {
+            return Err(RoughtimeError::Protocol("bad tag offset".into()));
+        }
+        map.insert(tag, buf[start..end].to_vec());
+    }
+    Ok(map)
+}
+
+fn get_tag<'a>(
+    msg: &'a std::collections::HashMap<[u8; 4], Vec<u8>>,
+    tag: [u8; 4],
+) -> Result<&'a [u8], RoughtimeError> {
+    msg.get(&tag)
+        .map(|v| v.as_slice())
+        .ok_or_else(|| RoughtimeError::Protocol(format!("missing tag {:?}", tag)))
+}
+
+/// Performs a single Roughtime request/response round trip against
+/// `host:port` and parses the response into a proof. The proof is not
+/// verified here and its `root_pubkey` is left zeroed; see
+/// [`get_attested_time`], which fills in the root key and runs [`verify`].
+pub fn request_timestamp(addr: &str) -> Result<TimestampProof, RoughtimeError> {
+    let mut nonce = [0u8; 64];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    // The request carries two tags (NONC, PAD); account for that header
+    // size directly rather than guessing, so the padded request actually
+    // reaches REQUEST_LEN and isn't dropped as an anti-amplification
+    // measure by the server.
+    let unpadded_len = encode_message(&[(TAG_NONC, &nonce), (TAG_PAD, &[])]).len();
+    let pad_len = REQUEST_LEN.saturating_sub(unpadded_len);
+    let padding = vec![0u8; pad_len];
+    let request = encode_message(&[(TAG_NONC, &nonce), (TAG_PAD, &padding)]);
+
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(RECV_TIMEOUT))?;
+    socket.connect(addr)?;
+    socket.send(&request)?;
+
+    let mut buf = vec![0u8; 4096];
+    let n = socket.recv(&mut buf)?;
+    buf.truncate(n);
+
+    verify_response(&buf, &nonce)
+}
+
+/// Re-derives and checks a proof's chain of trust without performing any
+/// network I/O. Used both right after a request and when verifying a
+/// previously-stored `.sig` file. `trusted_root_pubkey` is the caller's own
+/// copy of the server's long-term key; a proof's embedded `root_pubkey` is
+/// only a carrier for which key to use, not something to trust on its own,
+/// since a forged `.sig` could otherwise ship a throwaway root alongside an
+/// entirely self-consistent chain.
+pub fn verify(
+    proof: &TimestampProof,
+    trusted_root_pubkey: [u8; 32],
+) -> Result<(), RoughtimeError> {
+    if proof.root_pubkey != trusted_root_pubkey {
+        return Err(RoughtimeError::Verification(
+            "proof's root key does not match the trusted Roughtime root".into(),
+        ));
+    }
+    let root_key = VerifyingKey::from_bytes(&proof.root_pubkey)
+        .map_err(|e| RoughtimeError::Verification(format!("bad root key: {}", e)))?;
+
+    // 1. The long-term key must sign DELE.
+    let dele_sig = Signature::from_bytes(&proof.dele_sig);
+    let mut dele_signed = CONTEXT_DELE.to_vec();
+    dele_signed.extend_from_slice(&proof.dele);
+    root_key
+        .verify(&dele_signed, &dele_sig)
+        .map_err(|_| RoughtimeError::Verification("DELE signature invalid".into()))?;
+
+    let dele = decode_message(&proof.dele)?;
+    let delegated_pub = get_tag(&dele, *b"PUBK")?;
+    let mint = u64::from_le_bytes(
+        get_tag(&dele, TAG_MINT)?
+            .try_into()
+            .map_err(|_| RoughtimeError::Protocol("bad MINT".into()))?,
+    );
+    let maxt = u64::from_le_bytes(
+        get_tag(&dele, TAG_MAXT)?
+            .try_into()
+            .map_err(|_| RoughtimeError::Protocol("bad MAXT".into()))?,
+    );
+    let delegated_key = VerifyingKey::from_bytes(
+        delegated_pub
+            .try_into()
+            .map_err(|_| RoughtimeError::Protocol("bad delegated key length".into()))?,
+    )
+    .map_err(|e| RoughtimeError::Verification(format!("bad delegated key: {}", e)))?;
+
+    // 2. The delegated key must sign SREP.
+    let srep_sig = Signature::from_bytes(&proof.srep_sig);
+    let mut srep_signed = CONTEXT_SREP.to_vec();
+    srep_signed.extend_from_slice(&proof.srep);
+    delegated_key
+        .verify(&srep_signed, &srep_sig)
+        .map_err(|_| RoughtimeError::Verification("SREP signature invalid".into()))?;
+
+    let srep = decode_message(&proof.srep)?;
+    let root: [u8; 64] = get_tag(&srep, TAG_ROOT)?
+        .try_into()
+        .map_err(|_| RoughtimeError::Protocol("bad ROOT length".into()))?;
+
+    // 3. Recompute the Merkle leaf and fold it up PATH using INDX to pick
+    // left/right at each level, and confirm it equals ROOT.
+    let leaf = sha512(&[&[0x00], &proof.nonce[..]]);
+    let mut acc = leaf;
+    let mut index = proof.index;
+    for sibling in &proof.path {
+        acc = if index & 1 == 0 {
+            sha512(&[&[0x01], &acc, sibling])
+        } else {
+            sha512(&[&[0x01], sibling, &acc])
+        };
+        index >>= 1;
+    }
+    if acc != root {
+        return Err(RoughtimeError::Verification(
+            "Merkle path does not fold to ROOT".into(),
+        ));
+    }
+
+    // 4. MIDP must fall within the delegation's validity window.
+    if proof.midpoint_us < mint || proof.midpoint_us > maxt {
+        return Err(RoughtimeError::Verification(
+            "MIDP outside delegation validity window".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn verify_response(buf: &[u8], nonce: &[u8; 64]) -> Result<TimestampProof, RoughtimeError> {
+    let msg = decode_message(buf)?;
+    let srep_bytes = get_tag(&msg, TAG_SREP)?.to_vec();
+    let srep = decode_message(&srep_bytes)?;
+    let midpoint_us = u64::from_le_bytes(
+        get_tag(&srep, TAG_MIDP)?
+            .try_into()
+            .map_err(|_| RoughtimeError::Protocol("bad MIDP".into()))?,
+    );
+    let radius_us = u32::from_le_bytes(
+        get_tag(&srep, TAG_RADI)?
+            .try_into()
+            .map_err(|_| RoughtimeError::Protocol("bad RADI".into()))?,
+    );
+
+    let srep_sig: [u8; 64] = get_tag(&msg, TAG_SIG)?
+        .try_into()
+        .map_err(|_| RoughtimeError::Protocol("bad SIG length".into()))?;
+
+    let cert_bytes = get_tag(&msg, TAG_CERT)?.to_vec();
+    let cert = decode_message(&cert_bytes)?;
+    let dele_bytes = get_tag(&cert, TAG_DELE)?.to_vec();
+    let dele_sig: [u8; 64] = get_tag(&cert, TAG_SIG)?
+        .try_into()
+        .map_err(|_| RoughtimeError::Protocol("bad DELE SIG length".into()))?;
+
+    let index = u32::from_le_bytes(
+        get_tag(&msg, TAG_INDX)?
+            .try_into()
+            .map_err(|_| RoughtimeError::Protocol("bad INDX".into()))?,
+    );
+    let path_bytes = get_tag(&msg, TAG_PATH)?;
+    if path_bytes.len() % 64 != 0 {
+        return Err(RoughtimeError::Protocol("bad PATH length".into()));
+    }
+    let path: Vec<[u8; 64]> = path_bytes
+        .chunks_exact(64)
+        .map(|c| c.try_into().unwrap())
+        .collect();
+
+    let proof = TimestampProof {
+        nonce: *nonce,
+        root_pubkey: [0u8; 32], // filled in by the caller, who owns the trusted root key
+        dele: dele_bytes,
+        dele_sig,
+        srep: srep_bytes,
+        srep_sig,
+        index,
+        path,
+        midpoint_us,
+        radius_us,
+    };
+    Ok(proof)
+}
+
+/// Fetches and verifies an attested timestamp from `addr`, checking the
+/// response against `root_pubkey` (the server's long-term Ed25519 key).
+pub fn get_attested_time(
+    addr: &str,
+    root_pubkey: [u8; 32],
+) -> Result<TimestampProof, RoughtimeError> {
+    let mut proof = request_timestamp(addr)?;
+    proof.root_pubkey = root_pubkey;
+    verify(&proof, root_pubkey)?;
+    Ok(proof)
+}
+
+/// Serializes a proof to a flat binary blob suitable for embedding (hex
+/// encoded) in a `.sig` file.
+pub fn serialize(proof: &TimestampProof) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&proof.nonce);
+    out.extend_from_slice(&proof.root_pubkey);
+    out.extend_from_slice(&(proof.dele.len() as u32).to_le_bytes());
+    out.extend_from_slice(&proof.dele);
+    out.extend_from_slice(&proof.dele_sig);
+    out.extend_from_slice(&(proof.srep.len() as u32).to_le_bytes());
+    out.extend_from_slice(&proof.srep);
+    out.extend_from_slice(&proof.srep_sig);
+    out.extend_from_slice(&proof.index.to_le_bytes());
+    out.extend_from_slice(&(proof.path.len() as u32).to_le_bytes());
+    for sibling in &proof.path {
+        out.extend_from_slice(sibling);
+    }
+    out.extend_from_slice(&proof.midpoint_us.to_le_bytes());
+    out.extend_from_slice(&proof.radius_us.to_le_bytes());
+    out
+}
+
+/// Inverse of [`serialize`].
+pub fn deserialize(data: &[u8]) -> Result<TimestampProof, RoughtimeError> {
+    let mut pos = 0usize;
+    let mut take = |n: usize| -> Result<&[u8], RoughtimeError> {
+        if pos + n > data.len() {
+            return Err(RoughtimeError::Protocol("truncated proof".into()));
+        }
+        let slice = &data[pos..pos + n];
+        pos += n;
+        Ok(slice)
+    };
+
+    let nonce: [u8; 64] = take(64)?.try_into().unwrap();
+    let root_pubkey: [u8; 32] = take(32)?.try_into().unwrap();
+    let dele_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+    let dele = take(dele_len)?.to_vec();
+    let dele_sig: [u8; 64] = take(64)?.try_into().unwrap();
+    let srep_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+    let srep = take(srep_len)?.to_vec();
+    let srep_sig: [u8; 64] = take(64)?.try_into().unwrap();
+    let index = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let path_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+    let mut path = Vec::with_capacity(path_len);
+    for _ in 0..path_len {
+        path.push(take(64)?.try_into().unwrap());
+    }
+    let midpoint_us = u64::from_le_bytes(take(8)?.try_into().unwrap());
+    let radius_us = u32::from_le_bytes(take(4)?.try_into().unwrap());
+
+    Ok(TimestampProof {
+        nonce,
+        root_pubkey,
+        dele,
+        dele_sig,
+        srep,
+        srep_sig,
+        index,
+        path,
+        midpoint_us,
+        radius_us,
+    })
+}
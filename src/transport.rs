@@ -0,0 +1,244 @@
+//! Framed serial transport for the AT protocol.
+//!
+//! Every command is wrapped in a frame of `[len: u32 LE][crc32: u32 LE]
+//! [payload]`; the device is expected to echo a frame built the same way
+//! over its response, so a dropped or corrupted byte is caught by a CRC
+//! mismatch instead of silently truncating a line. Frames that time out
+//! or fail their CRC are retransmitted up to `retries` times. If the
+//! token disappears mid-command (USB renegotiation, a reset) the
+//! transport re-locates and re-initializes it and resumes from the start
+//! of the current frame rather than aborting the whole session.
+
+use serialport::{available_ports, Error as SerialError, ErrorKind, SerialPort, SerialPortType};
+use std::io::Write;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_RETRIES: u32 = 5;
+pub const DEFAULT_FRAME_TIMEOUT: Duration = Duration::from_millis(3000);
+const SERIAL_TIMEOUT: Duration = Duration::from_millis(400);
+const INIT_TIMEOUT: Duration = Duration::from_millis(1500);
+const PROBE_GRANUALITY: Duration = Duration::from_millis(25);
+const FRAME_HEADER_LEN: usize = 8;
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(SerialError),
+    NoDevice,
+    Timeout,
+    CrcMismatch,
+    RetriesExhausted,
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportError::Io(e) => write!(f, "I/O error: {}", e),
+            TransportError::NoDevice => write!(f, "token not found"),
+            TransportError::Timeout => write!(f, "timed out waiting for response frame"),
+            TransportError::CrcMismatch => write!(f, "response frame failed CRC check"),
+            TransportError::RetriesExhausted => {
+                write!(f, "command failed after exhausting all retries")
+            }
+        }
+    }
+}
+
+impl From<SerialError> for TransportError {
+    fn from(e: SerialError) -> Self {
+        TransportError::Io(e)
+    }
+}
+
+impl From<std::io::Error> for TransportError {
+    fn from(e: std::io::Error) -> Self {
+        TransportError::Io(SerialError::from(e))
+    }
+}
+
+/// Locates a device with the correct VID/PID and manufacturer/product
+/// strings in the list of ports returned by `serialport::available_ports`
+fn locate_token(mut ports: Vec<serialport::SerialPortInfo>) -> Option<String> {
+    loop {
+        let port = ports.pop()?;
+        if let SerialPortType::UsbPort(port_info) = port.port_type {
+            if port_info.vid == 0x0420
+                && port_info.pid == 0x2137
+                && port_info.manufacturer.unwrap_or("".to_string()) == "ABW"
+                && port_info.product.unwrap_or("".to_string()) == "STM32 NTRU Token"
+            {
+                return Some(port.port_name);
+            }
+        }
+    }
+}
+
+fn find_and_open() -> Result<(Box<dyn SerialPort>, String), TransportError> {
+    let ports = available_ports()?;
+    let port_name = locate_token(ports).ok_or(TransportError::NoDevice)?;
+    let port = serialport::new(&port_name, 115200)
+        .open()
+        .map_err(|_| TransportError::NoDevice)?;
+    Ok((port, port_name))
+}
+
+/// Initializes communication with the device by sending a newline and
+/// waiting for a response ending with a newline. Times out after
+/// `INIT_TIMEOUT`.
+fn init_communication(port: &mut Box<dyn SerialPort>) -> Result<(), TransportError> {
+    port.set_timeout(SERIAL_TIMEOUT)?;
+    port.write_all(b"\r\n")?;
+    let mut buf = [0u8; 64];
+    let mut res: Vec<u8> = Vec::with_capacity(1024);
+    let start = Instant::now();
+    loop {
+        if let Ok(n_read) = port.read(&mut buf) {
+            res.extend_from_slice(&buf[..n_read]);
+        }
+        if res.contains(&b'\n') {
+            return Ok(());
+        }
+        if Instant::now().duration_since(start) > INIT_TIMEOUT {
+            return Err(TransportError::NoDevice);
+        }
+        sleep(PROBE_GRANUALITY);
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+fn build_frame(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&crc32(payload).to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// True if `buf` looks like the plaintext startup banner `init_communication`
+/// waits for (arbitrary text terminated by a newline) rather than a binary
+/// frame. This only looks at bytes before a length header has even been
+/// parsed: the 8-byte header is effectively never going to contain a
+/// newline by chance, whereas a response payload legitimately can (the
+/// `AT+I` device-info text is multi-line), so the check can't be extended
+/// past the header without misfiring on ordinary multi-line responses.
+fn looks_like_banner(buf: &[u8]) -> bool {
+    buf.len() < FRAME_HEADER_LEN && buf.contains(&b'\n')
+}
+
+/// Reads exactly one frame, verifying its length header and CRC.
+/// Returns `Timeout` if the frame doesn't fully arrive within
+/// `frame_timeout`, `CrcMismatch` if it arrives corrupted, or `NoDevice`
+/// immediately (without waiting out `frame_timeout`) if the token's
+/// startup banner shows up instead of a frame, so the caller reconnects
+/// right away.
+fn read_frame(
+    port: &mut Box<dyn SerialPort>,
+    frame_timeout: Duration,
+) -> Result<Vec<u8>, TransportError> {
+    let mut buf = [0u8; 64];
+    let mut res: Vec<u8> = Vec::with_capacity(1024);
+    let start = Instant::now();
+    loop {
+        match port.read(&mut buf) {
+            Ok(n_read) if n_read > 0 => res.extend_from_slice(&buf[..n_read]),
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(_) => return Err(TransportError::NoDevice),
+        }
+        if looks_like_banner(&res) {
+            return Err(TransportError::NoDevice);
+        }
+        if res.len() >= FRAME_HEADER_LEN {
+            let len = u32::from_le_bytes(res[0..4].try_into().unwrap()) as usize;
+            if res.len() >= FRAME_HEADER_LEN + len {
+                let expected_crc = u32::from_le_bytes(res[4..8].try_into().unwrap());
+                let payload = res[FRAME_HEADER_LEN..FRAME_HEADER_LEN + len].to_vec();
+                if crc32(&payload) != expected_crc {
+                    return Err(TransportError::CrcMismatch);
+                }
+                return Ok(payload);
+            }
+        }
+        if Instant::now().duration_since(start) > frame_timeout {
+            return Err(TransportError::Timeout);
+        }
+        sleep(PROBE_GRANUALITY);
+    }
+}
+
+/// A reconnecting, CRC-checked, retrying transport for the AT protocol.
+pub struct Transport {
+    port: Box<dyn SerialPort>,
+    port_name: String,
+    pub retries: u32,
+    pub frame_timeout: Duration,
+}
+
+impl Transport {
+    /// Locates the token, opens its port, and performs the initial
+    /// handshake.
+    pub fn connect() -> Result<Self, TransportError> {
+        Self::connect_with(DEFAULT_RETRIES, DEFAULT_FRAME_TIMEOUT)
+    }
+
+    pub fn connect_with(retries: u32, frame_timeout: Duration) -> Result<Self, TransportError> {
+        let (mut port, port_name) = find_and_open()?;
+        init_communication(&mut port)?;
+        Ok(Transport {
+            port,
+            port_name,
+            retries,
+            frame_timeout,
+        })
+    }
+
+    /// Re-locates and re-opens the token after it disappears (a reset or
+    /// USB renegotiation), then re-runs the handshake.
+    fn reconnect(&mut self) -> Result<(), TransportError> {
+        let (mut port, port_name) = find_and_open()?;
+        init_communication(&mut port)?;
+        self.port = port;
+        self.port_name = port_name;
+        Ok(())
+    }
+
+    /// Sends a framed command and returns the device's framed response
+    /// payload. Retransmits the same frame (from scratch) up to
+    /// `self.retries` times on timeout or CRC mismatch, reconnecting to
+    /// the token first if it has dropped off the bus.
+    pub fn send_command(&mut self, cmd: &[u8]) -> Result<Vec<u8>, TransportError> {
+        let frame = build_frame(cmd);
+        let mut last_err = TransportError::RetriesExhausted;
+        for _ in 0..=self.retries {
+            if let Err(e) = self.port.write_all(&frame) {
+                last_err = e.into();
+                if self.reconnect().is_err() {
+                    continue;
+                }
+                continue;
+            }
+            match read_frame(&mut self.port, self.frame_timeout) {
+                Ok(payload) => return Ok(payload),
+                Err(TransportError::NoDevice) => {
+                    last_err = TransportError::NoDevice;
+                    let _ = self.reconnect();
+                }
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+}
+
+/// Returns true if a serialport error has kind `ErrorKind::NoDevice`,
+/// i.e. the device vanished.
+pub fn is_device_gone(err: &SerialError) -> bool {
+    matches!(err.kind, ErrorKind::NoDevice)
+}